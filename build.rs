@@ -1,7 +1,7 @@
 //! Build script for agc‑rs
 //! • Builds the vendored AGC static library (if AGC_DIR is not set).
-//! • Compiles the C++ bridge with the same Homebrew GCC that will be used
-//!   by rustc to link the final crate on macOS.
+//! • Compiles the C++ bridge with a toolchain resolved the same way the
+//!   `cc` crate resolves one, with an extra Homebrew-GCC probe on macOS.
 //! • Ensures all C++ symbols are resolved before final linking.
 
 use std::{
@@ -30,7 +30,208 @@ fn detect_homebrew_gcc() -> Option<(String, String)> {
     None
 }
 
+/// Whether a compiler path looks like a GCC/G++ binary rather than Clang.
+#[cfg(target_os = "macos")]
+fn is_gcc_like(compiler: &str) -> bool {
+    let name = PathBuf::from(compiler)
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or(compiler)
+        .to_owned();
+    name.contains("gcc") || name.contains("g++")
+}
+
+/// The C/C++ toolchain selected for the macOS build, plus enough detail to
+/// wire up GCC's static-runtime linking when that's what we picked.
+#[cfg(target_os = "macos")]
+struct MacToolchain {
+    cxx: String,
+    cc: String,
+    /// `lib/gcc/<ver>` directory, present only when `cxx`/`cc` resolved to GCC.
+    gcc_lib_dir: Option<PathBuf>,
+}
+
+/// Resolve the macOS toolchain the way the `cc` crate resolves one: honor an
+/// explicit `CXX`/`CC`, then `TARGET_CXX`/`TARGET_CC`, then `HOST_CXX`,
+/// before falling back to toolchain auto-detection and, only after that,
+/// probing for Homebrew GCC. If nothing yields a GCC and the user hasn't
+/// forced one, fall back to the platform default (Apple Clang) instead of
+/// panicking — AGC builds fine under Clang for most configurations.
+#[cfg(target_os = "macos")]
+fn resolve_mac_toolchain() -> MacToolchain {
+    let finish = |source: &str, cxx: String, cc: String| -> MacToolchain {
+        let gcc_lib_dir = if is_gcc_like(&cxx) {
+            Command::new(&cxx)
+                .arg("-print-file-name=libstdc++.a")
+                .output()
+                .ok()
+                .and_then(|out| {
+                    let path = PathBuf::from(String::from_utf8_lossy(&out.stdout).trim());
+                    path.is_absolute().then(|| path.parent().map(PathBuf::from)).flatten()
+                })
+        } else {
+            None
+        };
+        let kind = if gcc_lib_dir.is_some() { "GCC" } else { "Clang" };
+        println!("cargo:warning=Selected {kind} toolchain via {source}: {cxx}");
+        MacToolchain { cxx, cc, gcc_lib_dir }
+    };
+
+    let derive_cc = |cxx: &str| cxx.replace("g++", "gcc").replace("c++", "cc");
+
+    // 1. An explicit CXX/CC pins the compiler outright. CC is optional — a
+    //    caller who only sets CXX (the common case) still gets honored.
+    if let Ok(cxx) = env::var("CXX") {
+        let cc = env::var("CC").unwrap_or_else(|_| derive_cc(&cxx));
+        return finish("CXX/CC", cxx, cc);
+    }
+    // 2. TARGET_CXX/TARGET_CC — the `cc` crate's cross-compilation override.
+    if let Ok(cxx) = env::var("TARGET_CXX") {
+        let cc = env::var("TARGET_CC").unwrap_or_else(|_| derive_cc(&cxx));
+        return finish("TARGET_CXX/TARGET_CC", cxx, cc);
+    }
+    // 3. HOST_CXX — native host compiler override.
+    if let Ok(cxx) = env::var("HOST_CXX") {
+        let cc = env::var("HOST_CC").unwrap_or_else(|_| derive_cc(&cxx));
+        return finish("HOST_CXX", cxx, cc);
+    }
+    // 4. Toolchain auto-detection, matching what `cxx_build` itself would pick.
+    if let Ok(compiler) = cc::Build::new().cpp(true).try_get_compiler() {
+        let cxx = compiler.path().to_string_lossy().into_owned();
+        if is_gcc_like(&cxx) {
+            return finish("cc-crate auto-detection", cxx.clone(), cxx);
+        }
+    }
+    // 5. Homebrew GCC probe, since AGC needs GCC ≤13 for some SIMD intrinsics.
+    if let Some((prefix, ver)) = detect_homebrew_gcc() {
+        let cxx = format!("{prefix}/bin/g++-{ver}");
+        let cc = format!("{prefix}/bin/gcc-{ver}");
+        return finish("Homebrew GCC probe", cxx, cc);
+    }
+    // 6. Nothing forced a GCC — fall back to the default platform compiler.
+    println!(
+        "cargo:warning=No GCC toolchain found; falling back to the default platform compiler (Clang). \
+         Install one with `brew install gcc@13` and set CXX/CC if you need GCC-specific codegen."
+    );
+    MacToolchain {
+        cxx: "c++".to_owned(),
+        cc: "cc".to_owned(),
+        gcc_lib_dir: None,
+    }
+}
+
+/// Locate a system library via pkg-config (or vcpkg on Windows) and emit its
+/// `cargo:rustc-link-search`/`cargo:rustc-link-lib` lines. Returns `false`
+/// (emitting nothing) if the probe fails, so callers can fall back to the
+/// vendored copy. `pkg_config_name` and `vcpkg_port` often differ (e.g.
+/// pkg-config's `libzstd` vs. vcpkg's `zstd` port).
+fn probe_system_lib(pkg_config_name: &str, vcpkg_port: &str) -> bool {
+    if cfg!(target_os = "windows") {
+        vcpkg::find_package(vcpkg_port).is_ok()
+    } else {
+        pkg_config::Config::new().probe(pkg_config_name).is_ok()
+    }
+}
+
+/// Map the Cargo `TARGET` triple (and enabled target features) to the AGC
+/// `PLATFORM` make variable, so cross-builds and non-Mac ARM/x86 SIMD
+/// variants pick the right kernels instead of whatever AGC defaults to.
+/// Honors an `AGC_PLATFORM` override for callers who need to pin it.
+fn resolve_agc_platform() -> String {
+    if let Ok(forced) = env::var("AGC_PLATFORM") {
+        println!("cargo:warning=AGC_PLATFORM override: using platform '{forced}'");
+        return forced;
+    }
+
+    let target = env::var("TARGET").unwrap_or_default();
+    let features = env::var("CARGO_CFG_TARGET_FEATURE").unwrap_or_default();
+    let has_feature = |name: &str| features.split(',').any(|f| f == name);
+
+    let platform = if target.starts_with("aarch64") || target.starts_with("arm64") {
+        "arm8"
+    } else if target.starts_with("x86_64") || target.starts_with("i686") {
+        if has_feature("avx2") {
+            "avx2"
+        } else if has_feature("avx") {
+            "avx"
+        } else {
+            "sse2"
+        }
+    } else {
+        "generic"
+    };
+
+    println!("cargo:warning=Selected AGC platform '{platform}' for target '{target}'");
+    platform.to_owned()
+}
+
+/// The ISA (`-march=…` or `-m<ext>`) and `-mtune` flags matching an AGC `PLATFORM` value, so the C++
+/// bridge and the AGC static library agree on ISA.
+fn isa_flags_for_platform(platform: &str) -> &'static [&'static str] {
+    match platform {
+        "arm8" => &["-march=armv8-a", "-mtune=generic"],
+        "avx2" => &["-mavx2", "-mtune=generic"],
+        "avx" => &["-mavx", "-mtune=generic"],
+        "sse2" => &["-msse2", "-mtune=generic"],
+        _ => &[],
+    }
+}
+
+/// Whether the `dynamic-runtime` feature is enabled, in which case we prefer
+/// ordinary dynamic linking of the C++ runtime and zstd over the default
+/// `static-runtime` behavior of force-loading static archives.
+fn dynamic_runtime() -> bool {
+    cfg!(feature = "dynamic-runtime")
+}
+
+/// Build the vendored AGC library on Windows via CMake (NMake Makefiles for
+/// MSVC, MinGW Makefiles for MinGW; there is no `make` on Windows), returning
+/// the CMake install prefix; the static library is in its `lib` subdirectory.
+#[cfg(target_os = "windows")]
+fn build_agc_windows(agc_root: &std::path::Path, platform: &str) -> PathBuf {
+    println!("cargo:warning=Building vendored AGC via CMake …");
+
+    let mut config = cmake::Config::new(agc_root);
+    config.define("PLATFORM", platform).define("CMAKE_BUILD_TYPE", "Release");
+
+    if cfg!(target_env = "msvc") {
+        // Locate the active MSVC toolchain the same way the `cc` crate does
+        // (via its registry/vswhere probe) so CMake's generator matches it.
+        let target = env::var("TARGET").unwrap_or_default();
+        match cc::windows_registry::find_tool(&target, "cl.exe") {
+            Some(cl) => {
+                println!("cargo:warning=Using MSVC toolchain: {}", cl.path().display());
+                for (key, value) in cl.env() {
+                    config.env(key, value);
+                }
+            }
+            None => panic!(
+                "agc-rs: could not locate an MSVC toolchain for target '{target}' via the \
+                 registry/vswhere. Install the \"Desktop development with C++\" workload, or \
+                 build from a Developer Command Prompt."
+            ),
+        }
+        config.generator("NMake Makefiles");
+    } else {
+        // MinGW: point CMake at the MinGW g++/ar explicitly.
+        config
+            .define("CMAKE_CXX_COMPILER", "g++")
+            .define("CMAKE_C_COMPILER", "gcc")
+            .define("CMAKE_AR", "ar")
+            .generator("MinGW Makefiles");
+    }
+
+    config.build()
+}
+
 fn main() {
+    if cfg!(feature = "static-runtime") && cfg!(feature = "dynamic-runtime") {
+        panic!(
+            "agc-rs: features `static-runtime` and `dynamic-runtime` are mutually exclusive; \
+             enable exactly one (disable default features if you need `dynamic-runtime`)"
+        );
+    }
+
     /* ──────────────────────────────────────────────────────────────── */
     /* 1. Build / locate AGC                                           */
     /* ──────────────────────────────────────────────────────────────────────────────────────────────────────────────────── */
@@ -39,6 +240,25 @@ fn main() {
         .map(PathBuf::from)
         .unwrap_or_else(|_| manifest_dir.join("agc"));
 
+    #[cfg(target_os = "macos")]
+    let mac_toolchain = resolve_mac_toolchain();
+
+    let agc_platform = resolve_agc_platform();
+
+    #[cfg(target_os = "windows")]
+    let mut agc_lib_dir = agc_root.join("bin");
+
+    #[cfg(target_os = "windows")]
+    {
+        // MSVC's CMake generator produces `agc.lib`; MinGW follows Unix
+        // naming and produces `libagc.a`.
+        let prebuilt_name = if cfg!(target_env = "msvc") { "agc.lib" } else { "libagc.a" };
+        if !agc_root.join("bin").join(prebuilt_name).exists() {
+            agc_lib_dir = build_agc_windows(&agc_root, &agc_platform).join("lib");
+        }
+    }
+
+    #[cfg(not(target_os = "windows"))]
     if !agc_root.join("bin/libagc.a").exists() {
         println!("cargo:warning=Building vendored AGC …");
 
@@ -54,20 +274,15 @@ fn main() {
         };
 
         let mut make = Command::new(make_cmd);
-        make.current_dir(&agc_root).arg("-j");
+        make.current_dir(&agc_root).arg("-j").env("PLATFORM", &agc_platform);
 
         #[cfg(target_os = "macos")]
-        if let Some((prefix, ver)) = detect_homebrew_gcc() {
-            println!("cargo:warning=Using Homebrew GCC {ver} at {prefix}");
-            make.env("CC", format!("gcc-{ver}"))
-                .env("CXX", format!("g++-{ver}"));
-            if cfg!(target_arch = "aarch64") {
-                make.env("PLATFORM", "arm8");
+        {
+            make.env("CXX", &mac_toolchain.cxx).env("CC", &mac_toolchain.cc);
+            if mac_toolchain.gcc_lib_dir.is_some() {
+                // Force static linking in AGC build
+                make.env("LDFLAGS", "-static-libgcc -static-libstdc++");
             }
-            // Force static linking in AGC build
-            make.env("LDFLAGS", "-static-libgcc -static-libstdc++");
-        } else {
-            panic!("Homebrew GCC 11-13 is required on macOS. Install with: brew install gcc@13");
         }
 
         if !make.status().expect("failed to execute make").success() {
@@ -81,12 +296,10 @@ fn main() {
     #[cfg(target_os = "macos")]
     {
         // Set environment variables BEFORE creating the bridge
-        if let Some((prefix, ver)) = detect_homebrew_gcc() {
-            env::set_var("CXX", format!("{prefix}/bin/g++-{ver}"));
-            env::set_var("CC", format!("{prefix}/bin/gcc-{ver}"));
-            env::set_var("TARGET_CXX", format!("{prefix}/bin/g++-{ver}"));
-            env::set_var("TARGET_CC", format!("{prefix}/bin/gcc-{ver}"));
-        }
+        env::set_var("CXX", &mac_toolchain.cxx);
+        env::set_var("CC", &mac_toolchain.cc);
+        env::set_var("TARGET_CXX", &mac_toolchain.cxx);
+        env::set_var("TARGET_CC", &mac_toolchain.cc);
     }
 
     let mut bridge = cxx_build::bridge("src/lib.rs");
@@ -100,28 +313,29 @@ fn main() {
         .flag_if_supported("-std=c++20")
         .flag_if_supported("-fPIC");
 
+    for isa_flag in isa_flags_for_platform(&agc_platform) {
+        bridge.flag_if_supported(isa_flag);
+    }
+
     #[cfg(target_os = "macos")]
     {
-        if let Some((prefix, ver)) = detect_homebrew_gcc() {
-            // Still set the compiler explicitly
-            bridge.compiler(&format!("{prefix}/bin/g++-{ver}"));
-            
-            // Add ARM-specific flags to match AGC compilation
-            if cfg!(target_arch = "aarch64") {
-                bridge.flag("-march=armv8-a");
+        // Still set the compiler explicitly
+        bridge.compiler(&mac_toolchain.cxx);
+
+        if let Some(gcc_lib_dir) = &mac_toolchain.gcc_lib_dir {
+            if !dynamic_runtime() {
+                // Force static linking of ALL runtime libraries
+                bridge.flag("-static-libgcc");
+                bridge.flag("-static-libstdc++");
             }
 
-            // Force static linking of ALL runtime libraries
-            bridge.flag("-static-libgcc");
-            bridge.flag("-static-libstdc++");
-            
-            // Add GCC's lib path for finding the static libraries
-            bridge.flag(&format!("-L{prefix}/lib/gcc/{ver}"));
+            // Add GCC's lib path for finding the static (or dynamic) libraries
+            bridge.flag(&format!("-L{}", gcc_lib_dir.display()));
         }
     }
 
     #[cfg(not(target_os = "macos"))]
-    {
+    if !dynamic_runtime() {
         bridge
             .flag_if_supported("-static-libgcc")
             .flag_if_supported("-static-libstdc++");
@@ -133,51 +347,55 @@ fn main() {
     /* 3. Link configuration for macOS                                 */
     /* ──────────────────────────────────────────────────────────────── */
     #[cfg(target_os = "macos")]
-    if let Some((prefix, ver)) = detect_homebrew_gcc() {
-        let gcc_cmd = format!("{prefix}/bin/gcc-{ver}");
-        
-        // Use GCC to find the exact location of libgcc
-        if let Ok(output) = Command::new(&gcc_cmd)
-            .arg("-print-libgcc-file-name")
-            .output()
-        {
-            let libgcc_path = String::from_utf8_lossy(&output.stdout).trim().to_string();
-            if PathBuf::from(&libgcc_path).exists() {
-                println!("cargo:rustc-link-arg=-Wl,-force_load,{}", libgcc_path);
-            }
-        }
-        
+    if let Some(gcc_lib_dir) = &mac_toolchain.gcc_lib_dir {
         // Add all GCC lib directories
-        println!("cargo:rustc-link-search=native={prefix}/lib/gcc/{ver}");
-        println!("cargo:rustc-link-search=native={prefix}/lib");
-        
-        // Link libstdc++ 
-        let gcc_lib_path = PathBuf::from(&format!("{prefix}/lib/gcc/{ver}"));
-        let libstdcxx_path = gcc_lib_path.join("libstdc++.a");
-        if libstdcxx_path.exists() {
-            println!("cargo:rustc-link-arg=-Wl,-force_load,{}", libstdcxx_path.display());
-        }
-        
-        // Link libatomic.a for atomic operations
-        let libatomic_path = gcc_lib_path.join("libatomic.a");
-        if libatomic_path.exists() {
-            println!("cargo:rustc-link-arg=-Wl,-force_load,{}", libatomic_path.display());
-        }
-        
-        // For ARM64 on macOS, link additional runtime support
-        if cfg!(target_arch = "aarch64") {
-            // Link libgcc_eh for exception handling
-            if let Ok(output) = Command::new(&gcc_cmd)
-                .args(["-print-file-name=libgcc_eh.a"])
+        println!("cargo:rustc-link-search=native={}", gcc_lib_dir.display());
+        println!("cargo:rustc-link-search=native={}", gcc_lib_dir.join("../..").display());
+
+        if dynamic_runtime() {
+            // Ordinary dynamic linking, plus an rpath so the resulting
+            // binary finds GCC's shared runtime at run time.
+            println!("cargo:rustc-link-lib=dylib=stdc++");
+            println!("cargo:rustc-link-arg=-Wl,-rpath,{}", gcc_lib_dir.display());
+        } else {
+            // Use GCC to find the exact location of libgcc
+            if let Ok(output) = Command::new(&mac_toolchain.cxx)
+                .arg("-print-libgcc-file-name")
                 .output()
             {
-                let libgcc_eh_path = String::from_utf8_lossy(&output.stdout).trim().to_string();
-                if PathBuf::from(&libgcc_eh_path).exists() && libgcc_eh_path != "libgcc_eh.a" {
-                    println!("cargo:rustc-link-arg=-Wl,-force_load,{}", libgcc_eh_path);
+                let libgcc_path = String::from_utf8_lossy(&output.stdout).trim().to_string();
+                if PathBuf::from(&libgcc_path).exists() {
+                    println!("cargo:rustc-link-arg=-Wl,-force_load,{}", libgcc_path);
+                }
+            }
+
+            // Link libstdc++
+            let libstdcxx_path = gcc_lib_dir.join("libstdc++.a");
+            if libstdcxx_path.exists() {
+                println!("cargo:rustc-link-arg=-Wl,-force_load,{}", libstdcxx_path.display());
+            }
+
+            // Link libatomic.a for atomic operations
+            let libatomic_path = gcc_lib_dir.join("libatomic.a");
+            if libatomic_path.exists() {
+                println!("cargo:rustc-link-arg=-Wl,-force_load,{}", libatomic_path.display());
+            }
+
+            // For ARM64 on macOS, link additional runtime support
+            if cfg!(target_arch = "aarch64") {
+                // Link libgcc_eh for exception handling
+                if let Ok(output) = Command::new(&mac_toolchain.cxx)
+                    .args(["-print-file-name=libgcc_eh.a"])
+                    .output()
+                {
+                    let libgcc_eh_path = String::from_utf8_lossy(&output.stdout).trim().to_string();
+                    if PathBuf::from(&libgcc_eh_path).exists() && libgcc_eh_path != "libgcc_eh.a" {
+                        println!("cargo:rustc-link-arg=-Wl,-force_load,{}", libgcc_eh_path);
+                    }
                 }
             }
         }
-        
+
         // Also link the shared libgcc_s for any remaining symbols
         println!("cargo:rustc-link-lib=dylib=gcc_s.1");
     }
@@ -185,43 +403,193 @@ fn main() {
     /* ──────────────────────────────────────────────────────────────── */
     /* 4. Link against AGC & dependencies                              */
     /* ──────────────────────────────────────────────────────────────────────────────────────────────────────────────────── */
+    #[cfg(target_os = "windows")]
+    println!("cargo:rustc-link-search=native={}", agc_lib_dir.display());
+    #[cfg(not(target_os = "windows"))]
     println!("cargo:rustc-link-search=native={}", agc_root.join("bin").display());
     println!("cargo:rustc-link-lib=static=agc");
 
-    // IMPORTANT: Force static linking of zstd to avoid runtime dependency
-    println!(
-        "cargo:rustc-link-search=native={}",
-        agc_root.join("3rd_party/zstd/lib").display()
-    );
-    println!("cargo:rustc-link-lib=static=zstd");
-    
-    // Also check if there's a system zstd we need to handle
-    #[cfg(target_os = "macos")]
-    {
-        // Add common Homebrew library paths where zstd might be
-        println!("cargo:rustc-link-search=native=/opt/homebrew/lib");
-        println!("cargo:rustc-link-search=native=/usr/local/lib");
-        
-        // If the static library exists in the AGC directory, force load it
-        let zstd_static = agc_root.join("3rd_party/zstd/lib/libzstd.a");
-        if zstd_static.exists() {
-            println!("cargo:rustc-link-arg=-Wl,-force_load,{}", zstd_static.display());
+    // Under the `system-libs` feature, prefer an audited system zstd/zlib
+    // (found via pkg-config, or vcpkg on Windows). If a probe fails, zstd falls
+    // back to the vendored copy and zlib to a plain `z`/`zlibstatic` link.
+    let used_system_zstd = cfg!(feature = "system-libs") && probe_system_lib("libzstd", "zstd");
+    let used_system_zlib = cfg!(feature = "system-libs") && probe_system_lib("zlib", "zlib");
+
+    if !used_system_zstd {
+        let zstd_lib_dir = agc_root.join("3rd_party/zstd/lib");
+        println!("cargo:rustc-link-search=native={}", zstd_lib_dir.display());
+
+        if dynamic_runtime() {
+            println!("cargo:rustc-link-lib=dylib=zstd");
+            println!("cargo:rustc-link-arg=-Wl,-rpath,{}", zstd_lib_dir.display());
+        } else {
+            // IMPORTANT: Force static linking of zstd to avoid runtime dependency
+            println!("cargo:rustc-link-lib=static=zstd");
+
+            // On macOS, also force-load the vendored archive
+            #[cfg(target_os = "macos")]
+            {
+                // If the static library exists in the AGC directory, force load it
+                let zstd_static = zstd_lib_dir.join("libzstd.a");
+                if zstd_static.exists() {
+                    println!("cargo:rustc-link-arg=-Wl,-force_load,{}", zstd_static.display());
+                }
+            }
+        }
+
+        // Also check common Homebrew library paths where zstd might live.
+        #[cfg(target_os = "macos")]
+        {
+            println!("cargo:rustc-link-search=native=/opt/homebrew/lib");
+            println!("cargo:rustc-link-search=native=/usr/local/lib");
         }
     }
-    
+
     // Common system libraries
-    println!("cargo:rustc-link-lib=z");
+    if !used_system_zlib {
+        #[cfg(target_os = "windows")]
+        println!("cargo:rustc-link-lib=zlibstatic");
+        #[cfg(not(target_os = "windows"))]
+        println!("cargo:rustc-link-lib=z");
+    }
+
+    // `pthread` is POSIX-only; Windows uses its native threading API (MSVC)
+    // or `winpthread` (MinGW) instead.
+    #[cfg(unix)]
     println!("cargo:rustc-link-lib=pthread");
-    
-    // On non-macOS, link libstdc++ normally
-    #[cfg(not(target_os = "macos"))]
+    #[cfg(all(target_os = "windows", target_env = "gnu"))]
+    println!("cargo:rustc-link-lib=winpthread");
+
+    // On non-macOS Unix, link libstdc++; on MSVC, link the static C runtime
+    // (libcmt). MinGW links libstdc++/libgcc statically by default, or
+    // dynamically under the `dynamic-runtime` feature.
+    #[cfg(all(unix, not(target_os = "macos")))]
     println!("cargo:rustc-link-lib=stdc++");
+    #[cfg(all(target_os = "windows", target_env = "gnu"))]
+    {
+        if dynamic_runtime() {
+            println!("cargo:rustc-link-lib=dylib=stdc++");
+            println!("cargo:rustc-link-lib=dylib=gcc_s");
+        } else {
+            println!("cargo:rustc-link-lib=static=stdc++");
+            println!("cargo:rustc-link-lib=static=gcc");
+        }
+    }
+    #[cfg(all(target_os = "windows", target_env = "msvc"))]
+    println!("cargo:rustc-link-lib=libcmt");
 
     /* ──────────────────────────────────────────────────────────────── */
     /* 5. Re‑run triggers                                              */
     /* ──────────────────────────────────────────────────────────────────────────────────────────────────────────────────── */
     println!("cargo:rerun-if-env-changed=AGC_DIR");
+    println!("cargo:rerun-if-env-changed=AGC_PLATFORM");
+    println!("cargo:rerun-if-env-changed=CXX");
+    println!("cargo:rerun-if-env-changed=CC");
+    println!("cargo:rerun-if-env-changed=TARGET_CXX");
+    println!("cargo:rerun-if-env-changed=TARGET_CC");
+    println!("cargo:rerun-if-env-changed=HOST_CXX");
+    println!("cargo:rerun-if-env-changed=HOST_CC");
     println!("cargo:rerun-if-changed=src/lib.rs");
     println!("cargo:rerun-if-changed=src/agc_bridge.cpp");
     println!("cargo:rerun-if-changed=src/agc_bridge.h");
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+    /// Run `f` with the given env vars set (`None` means unset), restoring
+    /// the previous values afterwards. Serialized via `ENV_LOCK` since env
+    /// vars are process-global and tests run concurrently by default.
+    fn with_env<T>(vars: &[(&str, Option<&str>)], f: impl FnOnce() -> T) -> T {
+        let _guard = ENV_LOCK.lock().unwrap();
+        let previous: Vec<_> = vars.iter().map(|(k, _)| (*k, env::var(k).ok())).collect();
+        for (k, v) in vars {
+            match v {
+                Some(v) => env::set_var(k, v),
+                None => env::remove_var(k),
+            }
+        }
+        let result = f();
+        for (k, v) in previous {
+            match v {
+                Some(v) => env::set_var(k, v),
+                None => env::remove_var(k),
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn aarch64_target_selects_arm8() {
+        let platform = with_env(
+            &[
+                ("AGC_PLATFORM", None),
+                ("TARGET", Some("aarch64-apple-darwin")),
+                ("CARGO_CFG_TARGET_FEATURE", None),
+            ],
+            resolve_agc_platform,
+        );
+        assert_eq!(platform, "arm8");
+    }
+
+    #[test]
+    fn x86_64_without_avx_selects_sse2() {
+        let platform = with_env(
+            &[
+                ("AGC_PLATFORM", None),
+                ("TARGET", Some("x86_64-unknown-linux-gnu")),
+                ("CARGO_CFG_TARGET_FEATURE", None),
+            ],
+            resolve_agc_platform,
+        );
+        assert_eq!(platform, "sse2");
+    }
+
+    #[test]
+    fn x86_64_with_avx2_feature_selects_avx2() {
+        let platform = with_env(
+            &[
+                ("AGC_PLATFORM", None),
+                ("TARGET", Some("x86_64-unknown-linux-gnu")),
+                ("CARGO_CFG_TARGET_FEATURE", Some("sse,sse2,avx,avx2")),
+            ],
+            resolve_agc_platform,
+        );
+        assert_eq!(platform, "avx2");
+    }
+
+    #[test]
+    fn unknown_target_selects_generic() {
+        let platform = with_env(
+            &[
+                ("AGC_PLATFORM", None),
+                ("TARGET", Some("riscv64gc-unknown-linux-gnu")),
+                ("CARGO_CFG_TARGET_FEATURE", None),
+            ],
+            resolve_agc_platform,
+        );
+        assert_eq!(platform, "generic");
+    }
+
+    #[test]
+    fn agc_platform_override_wins() {
+        let platform = with_env(
+            &[("AGC_PLATFORM", Some("sse2")), ("TARGET", Some("aarch64-apple-darwin"))],
+            resolve_agc_platform,
+        );
+        assert_eq!(platform, "sse2");
+    }
+
+    #[test]
+    fn isa_flags_matches_known_platforms() {
+        assert_eq!(isa_flags_for_platform("arm8"), &["-march=armv8-a", "-mtune=generic"]);
+        assert_eq!(isa_flags_for_platform("avx2"), &["-mavx2", "-mtune=generic"]);
+        assert_eq!(isa_flags_for_platform("avx"), &["-mavx", "-mtune=generic"]);
+        assert_eq!(isa_flags_for_platform("sse2"), &["-msse2", "-mtune=generic"]);
+        assert_eq!(isa_flags_for_platform("generic").len(), 0);
+    }
+}
@@ -1,3 +1,4 @@
+use std::io::Write;
 use std::path::Path;
 
 #[cxx::bridge]
@@ -16,13 +17,13 @@ mod ffi {
         fn close_archive(decompressor: Pin<&mut AGCDecompressor>) -> bool;
         fn is_opened(decompressor: &AGCDecompressor) -> bool;
 
-        fn get_contig_string(
+        fn get_contig_bytes(
             decompressor: Pin<&mut AGCDecompressor>,
             sample_name: &str,
             contig_name: &str,
             start: i32,
             end: i32,
-        ) -> Result<String>;
+        ) -> Result<Vec<u8>>;
 
         fn get_contig_length(
             decompressor: &AGCDecompressor,
@@ -69,14 +70,18 @@ impl AGCFile {
         ffi::is_opened(&self.decompressor)
     }
 
-    pub fn get_contig_sequence(
+    /// Raw ASCII bytes of `[start, end]`, without the UTF-8 validity check
+    /// `get_contig_sequence` pays for. DNA is plain ASCII, so this skips a
+    /// validation scan over multi-megabase contigs for callers that just
+    /// want bytes.
+    pub fn get_contig_bytes(
         &mut self,
         sample_name: &str,
         contig_name: &str,
         start: i32,
         end: i32,
-    ) -> Result<String, String> {
-        ffi::get_contig_string(
+    ) -> Result<Vec<u8>, String> {
+        ffi::get_contig_bytes(
             self.decompressor.pin_mut(),
             sample_name,
             contig_name,
@@ -86,18 +91,67 @@ impl AGCFile {
         .map_err(|e| e.to_string())
     }
 
-    pub fn get_full_contig(
+    /// Byte-slice equivalent of [`AGCFile::get_full_contig`].
+    pub fn get_full_contig_bytes(
         &mut self,
         sample_name: &str,
         contig_name: &str,
-    ) -> Result<String, String> {
+    ) -> Result<Vec<u8>, String> {
         let length = self.get_contig_length(sample_name, contig_name);
         if length <= 0 {
             return Err(format!(
                 "Contig {contig_name}@{sample_name} not found or has zero length"
             ));
         }
-        self.get_contig_sequence(sample_name, contig_name, 0, (length - 1) as i32)
+        self.get_contig_bytes(sample_name, contig_name, 0, (length - 1) as i32)
+    }
+
+    /// Stream the full contig into `writer` in fixed-size chunks, so whole
+    /// genome extraction doesn't require holding the full sequence in memory.
+    pub fn write_contig_to<W: Write>(
+        &mut self,
+        sample_name: &str,
+        contig_name: &str,
+        writer: &mut W,
+    ) -> Result<(), String> {
+        const CHUNK_LEN: i64 = 1 << 20; // 1 MiB per chunk
+
+        let length = self.get_contig_length(sample_name, contig_name);
+        if length <= 0 {
+            return Err(format!(
+                "Contig {contig_name}@{sample_name} not found or has zero length"
+            ));
+        }
+
+        let mut start = 0i64;
+        while start < length {
+            let end = (start + CHUNK_LEN - 1).min(length - 1);
+            let chunk =
+                self.get_contig_bytes(sample_name, contig_name, start as i32, end as i32)?;
+            writer.write_all(&chunk).map_err(|e| e.to_string())?;
+            start = end + 1;
+        }
+        Ok(())
+    }
+
+    pub fn get_contig_sequence(
+        &mut self,
+        sample_name: &str,
+        contig_name: &str,
+        start: i32,
+        end: i32,
+    ) -> Result<String, String> {
+        let bytes = self.get_contig_bytes(sample_name, contig_name, start, end)?;
+        String::from_utf8(bytes).map_err(|e| e.to_string())
+    }
+
+    pub fn get_full_contig(
+        &mut self,
+        sample_name: &str,
+        contig_name: &str,
+    ) -> Result<String, String> {
+        let bytes = self.get_full_contig_bytes(sample_name, contig_name)?;
+        String::from_utf8(bytes).map_err(|e| e.to_string())
     }
 
     pub fn get_contig_length(&self, sample_name: &str, contig_name: &str) -> i64 {
@@ -13,3 +13,23 @@ fn test_debug_impl() {
     assert!(debug_str.contains("AGCFile"));
     assert!(debug_str.contains("is_opened"));
 }
+
+#[test]
+fn test_get_contig_bytes_on_unopened_archive_errs() {
+    let mut agc = AGCFile::new();
+    assert!(agc.get_contig_bytes("sample", "contig", 0, 9).is_err());
+}
+
+#[test]
+fn test_get_full_contig_bytes_missing_contig_errs() {
+    let mut agc = AGCFile::new();
+    assert!(agc.get_full_contig_bytes("sample", "contig").is_err());
+}
+
+#[test]
+fn test_write_contig_to_missing_contig_errs() {
+    let mut agc = AGCFile::new();
+    let mut buf = Vec::new();
+    assert!(agc.write_contig_to("sample", "contig", &mut buf).is_err());
+    assert!(buf.is_empty());
+}